@@ -1,7 +1,6 @@
 use std::cmp::min;
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::rc::Weak;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 
 /// A transformation to an index in a vector.
 /// 
@@ -14,6 +13,10 @@ pub enum Edit<'a, T: 'a + Eq> {
     Insert(&'a T),
     /// Delete the element from the input vector.
     Delete,
+    /// Replace the element at the current position with this one. Only produced by
+    /// `diff_weighted` and `diff_dijkstra` when a cost spec with a `substitute` cost
+    /// is supplied.
+    Substitute(&'a T),
     /// Keep the original character in the output.
     Keep
 }
@@ -23,90 +26,343 @@ impl<'a, T: 'a + Eq> Clone for Edit<'a, T> {
         match self {
             Edit::Insert(t) => Edit::Insert(t),
             Edit::Delete => Edit::Delete,
+            Edit::Substitute(t) => Edit::Substitute(t),
             Edit::Keep => Edit::Keep,
         }
     }
 }
 
-struct GridSquare<'a, T: 'a + Eq> {
-    cost: i32,
-    from: Option<Weak<RefCell<GridSquare<'a, T>>>>,
-    edit: Edit<'a, T>
+/// Returns the length of the common prefix and (non-overlapping) common suffix of
+/// two sequences of lengths `a_len`/`b_len`, as judged by `eq(i, j)`.
+///
+/// Used to trim the region a DP grid has to cover down to where `a` and `b`
+/// actually differ.
+fn common_affixes<E: Fn(usize, usize) -> bool>(a_len: usize, b_len: usize, eq: E) -> (usize, usize) {
+    let mut prefix_len = 0;
+    while prefix_len < a_len && prefix_len < b_len && eq(prefix_len, prefix_len) {
+        prefix_len += 1;
+    }
+
+    let mut suffix_len = 0;
+    while suffix_len < a_len - prefix_len
+        && suffix_len < b_len - prefix_len
+        && eq(a_len - 1 - suffix_len, b_len - 1 - suffix_len)
+    {
+        suffix_len += 1;
+    }
+
+    (prefix_len, suffix_len)
 }
 
-impl<'a, T: 'a + Eq> GridSquare<'a, T> {
-    fn path(&self) -> Vec<Edit<'a, T>> {
-        self.from.as_ref().map(|from| {
-            let rc = from.upgrade().unwrap();
-            let mut path: Vec<Edit<'a, T>> = rc.borrow().path();
-            path.push(self.edit.clone());
-            return path;
-        }).unwrap_or(Vec::new())
+/// Core of `diff`/`diff_by_key`: trims the common prefix/suffix (as judged by
+/// `eq`), fills a flat cost matrix over the remaining middle, and backtracks to
+/// reconstruct the edits. `eq(i, j)` reports whether `a[i]` and `b[j]` (by
+/// whatever notion of equality the caller wants) should be treated as a `Keep`.
+fn diff_core<'a, T: Eq, E: Fn(usize, usize) -> bool>(a: &'a [T], b: &'a [T], eq: E) -> Vec<Edit<'a, T>> {
+    let (prefix_len, suffix_len) = common_affixes(a.len(), b.len(), &eq);
+
+    let a_mid = &a[prefix_len..a.len() - suffix_len];
+    let b_mid = &b[prefix_len..b.len() - suffix_len];
+
+    let rows = a_mid.len() + 1;
+    let cols = b_mid.len() + 1;
+
+    // `cache[i * cols + j]` holds the edit distance between `a_mid[..i]` and
+    // `b_mid[..j]`. Row 0 and column 0 are the base cases of deleting or
+    // inserting every remaining element.
+    let mut cache: Vec<usize> = vec![0; rows * cols];
+
+    for i in 0..rows {
+        cache[i * cols] = i;
+    }
+
+    for j in 0..cols {
+        cache[j] = j;
+    }
+
+    for i in 1..rows {
+        for j in 1..cols {
+            let deletion_cost = 1 + cache[(i - 1) * cols + j];
+            let insertion_cost = 1 + cache[i * cols + (j - 1)];
+
+            cache[i * cols + j] = if eq(prefix_len + i - 1, prefix_len + j - 1) {
+                min(min(insertion_cost, deletion_cost), cache[(i - 1) * cols + (j - 1)])
+            } else {
+                min(insertion_cost, deletion_cost)
+            };
+        }
+    }
+
+    // Walk backwards from the bottom-right corner, picking at each cell
+    // whichever neighbor its cost was derived from, then reverse the
+    // collected edits to put them back in forward order.
+    let mut mid_edits: Vec<Edit<'a, T>> = Vec::with_capacity(rows + cols);
+    let mut i = rows - 1;
+    let mut j = cols - 1;
+
+    while i > 0 || j > 0 {
+        if j > 0 && cache[i * cols + j] == cache[i * cols + (j - 1)] + 1 {
+            mid_edits.push(Edit::Insert(&b_mid[j - 1]));
+            j -= 1;
+        } else if i > 0 && cache[i * cols + j] == cache[(i - 1) * cols + j] + 1 {
+            mid_edits.push(Edit::Delete);
+            i -= 1;
+        } else {
+            mid_edits.push(Edit::Keep);
+            i -= 1;
+            j -= 1;
+        }
     }
+
+    mid_edits.reverse();
+
+    let mut edits = vec![Edit::Keep; prefix_len];
+    edits.extend(mid_edits);
+    edits.extend(vec![Edit::Keep; suffix_len]);
+    edits
 }
 
 /// Returns the edits required to change `a` into `b`
-/// 
+///
 /// Edits are applied to each character in `a`. See `Edit` to determine what each type of Edit does.
-pub fn diff<'a, T: Eq>(a: &'a Vec<T>, b: &'a Vec<T>) -> Vec<Edit<'a, T>> {
-    let grid: Vec<Vec<Rc<RefCell<GridSquare<'a, T>>>>> = (0..a.len() + 1).map(|_a| {
-        return (0..b.len() + 1).map(|_b| {
-            return Rc::new(RefCell::new(GridSquare{
-                cost: 0,
-                from: None,
-                edit: Edit::Keep
-            }));
-        }).collect::<Vec<Rc<RefCell<GridSquare<'a, T>>>>>();
-    }).collect::<Vec<Vec<Rc<RefCell<GridSquare<'a, T>>>>>>();
-
-    for i in 1..a.len() + 1 {
-        let mut grid_square = grid[i][0].borrow_mut();
-        grid_square.cost = i as i32;
-        grid_square.from = Some(Rc::downgrade(&grid[i - 1][0]));
-        grid_square.edit = Edit::Delete;
-    }
-
-    for j in 1..b.len() + 1 {
-        let mut grid_square = grid[0][j].borrow_mut();
-        grid_square.cost = j as i32;
-        grid_square.from = Some(Rc::downgrade(&grid[0][j - 1]));
-        grid_square.edit = Edit::Insert(&b[j - 1]);
-    }
-
-    for i in 1..a.len() + 1 {
-        for j in 1..b.len() + 1 {
-            let deletion_cell = &grid[i - 1][j];
-            let insertion_cell = &grid[i][j - 1];
-            let keep_cell = &grid[i - 1][j - 1];
-            let deletion_cost = 1 + deletion_cell.borrow().cost;
-            let insertion_cost = 1 + insertion_cell.borrow().cost;
-            let keep_cost = keep_cell.borrow().cost;
-
-            let min_cost = if a[i - 1] == b[j - 1] {
-                min(min(insertion_cost, deletion_cost), keep_cost)
+pub fn diff<'a, T: Eq>(a: &'a [T], b: &'a [T]) -> Vec<Edit<'a, T>> {
+    diff_core(a, b, |i, j| a[i] == b[j])
+}
+
+/// The cost of substituting one element for another, or `None` to forbid
+/// substitution entirely.
+pub type SubstituteCost<T> = Option<Box<dyn Fn(&T, &T) -> u32>>;
+
+/// Per-operation costs for `diff_weighted`.
+///
+/// `substitute` gives the cost of replacing an `a` element with a `b` element in
+/// place; pass `None` to forbid substitution entirely, in which case an unequal
+/// pair is always expressed as a delete followed by an insert.
+pub struct Costs<T> {
+    pub insert: u32,
+    pub delete: u32,
+    pub substitute: SubstituteCost<T>,
+}
+
+/// Returns the edits required to change `a` into `b` under the given per-operation
+/// `costs`.
+///
+/// Unlike `diff`, an unequal pair of elements may be emitted as a single
+/// `Edit::Substitute` rather than a `Delete`/`Insert` pair, whenever `costs.substitute`
+/// says that's cheaper.
+pub fn diff_weighted<'a, T: Eq>(a: &'a [T], b: &'a [T], costs: &Costs<T>) -> Vec<Edit<'a, T>> {
+    // As in `diff`, trim the common prefix/suffix first: `Keep` always costs
+    // nothing, so matching greedily at the ends is never worse than matching
+    // the same elements elsewhere, no matter what `costs` says.
+    let (prefix_len, suffix_len) = common_affixes(a.len(), b.len(), |i, j| a[i] == b[j]);
+
+    let a_mid = &a[prefix_len..a.len() - suffix_len];
+    let b_mid = &b[prefix_len..b.len() - suffix_len];
+
+    let rows = a_mid.len() + 1;
+    let cols = b_mid.len() + 1;
+
+    let mut cache: Vec<u32> = vec![0; rows * cols];
+
+    for (i, cell) in cache.iter_mut().step_by(cols).enumerate() {
+        *cell = i as u32 * costs.delete;
+    }
+
+    for (j, cell) in cache[..cols].iter_mut().enumerate() {
+        *cell = j as u32 * costs.insert;
+    }
+
+    for i in 1..rows {
+        for j in 1..cols {
+            let deletion_cost = costs.delete + cache[(i - 1) * cols + j];
+            let insertion_cost = costs.insert + cache[i * cols + (j - 1)];
+            let diagonal_cost = cache[(i - 1) * cols + (j - 1)];
+
+            let sub_cost = if a_mid[i - 1] == b_mid[j - 1] {
+                Some(diagonal_cost)
             } else {
-                min(insertion_cost, deletion_cost)
+                costs.substitute.as_ref().map(|sub| sub(&a_mid[i - 1], &b_mid[j - 1]) + diagonal_cost)
+            };
+
+            cache[i * cols + j] = match sub_cost {
+                Some(sub_cost) => min(min(insertion_cost, deletion_cost), sub_cost),
+                None => min(insertion_cost, deletion_cost),
             };
+        }
+    }
+
+    let mut mid_edits: Vec<Edit<'a, T>> = Vec::with_capacity(rows + cols);
+    let mut i = rows - 1;
+    let mut j = cols - 1;
+
+    while i > 0 || j > 0 {
+        let diagonal_cost = if i > 0 && j > 0 { Some(cache[(i - 1) * cols + (j - 1)]) } else { None };
 
-            let mut current_grid_square = grid[i][j].borrow_mut();
+        if i > 0 && j > 0 && a_mid[i - 1] == b_mid[j - 1] && Some(cache[i * cols + j]) == diagonal_cost {
+            mid_edits.push(Edit::Keep);
+            i -= 1;
+            j -= 1;
+        } else if i > 0 && j > 0 && diagonal_cost.is_some_and(|diagonal_cost| {
+            costs.substitute.as_ref().is_some_and(|sub| cache[i * cols + j] == sub(&a_mid[i - 1], &b_mid[j - 1]) + diagonal_cost)
+        }) {
+            mid_edits.push(Edit::Substitute(&b_mid[j - 1]));
+            i -= 1;
+            j -= 1;
+        } else if j > 0 && cache[i * cols + j] == cache[i * cols + (j - 1)] + costs.insert {
+            mid_edits.push(Edit::Insert(&b_mid[j - 1]));
+            j -= 1;
+        } else {
+            mid_edits.push(Edit::Delete);
+            i -= 1;
+        }
+    }
+
+    mid_edits.reverse();
+
+    let mut edits = vec![Edit::Keep; prefix_len];
+    edits.extend(mid_edits);
+    edits.extend(vec![Edit::Keep; suffix_len]);
+    edits
+}
+
+/// Per-element costs for `diff_dijkstra`.
+///
+/// Unlike `Costs`, `insert` and `delete` see the specific element being inserted
+/// or deleted, so the cost of an operation can depend on what it operates on
+/// (e.g. inserting a space is cheap, inserting a word is expensive).
+pub struct ElementCosts<T> {
+    pub insert: Box<dyn Fn(&T) -> u32>,
+    pub delete: Box<dyn Fn(&T) -> u32>,
+    pub substitute: SubstituteCost<T>,
+}
+
+/// A `(cost, i, j)` frontier node ordered so that `BinaryHeap` (a max-heap) pops
+/// the lowest-cost node first.
+struct DijkstraNode(u32, usize, usize);
+
+impl PartialEq for DijkstraNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for DijkstraNode {}
+
+impl Ord for DijkstraNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
 
-            if insertion_cost == min_cost {
-                current_grid_square.cost = insertion_cost;
-                current_grid_square.from = Some(Rc::downgrade(insertion_cell));
-                current_grid_square.edit = Edit::Insert(&b[j - 1]);
-            } else if deletion_cost == min_cost {
-                current_grid_square.cost = deletion_cost;
-                current_grid_square.from = Some(Rc::downgrade(deletion_cell));
-                current_grid_square.edit = Edit::Delete;
+impl PartialOrd for DijkstraNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Returns the edits required to change `a` into `b` under the given per-element
+/// `costs`, found via Dijkstra's algorithm over the `(i, j)` grid.
+///
+/// This is strictly more flexible than `diff_weighted`: the cost of an insert or
+/// delete may depend on the element, where `Costs` uses one fixed cost for every
+/// element.
+pub fn diff_dijkstra<'a, T: Eq>(a: &'a [T], b: &'a [T], costs: &ElementCosts<T>) -> Vec<Edit<'a, T>> {
+    let rows = a.len() + 1;
+    let cols = b.len() + 1;
+
+    let mut dist: Vec<u32> = vec![u32::MAX; rows * cols];
+    let mut prev: Vec<Option<Edit<'a, T>>> = (0..rows * cols).map(|_| None).collect();
+    let mut visited: Vec<bool> = vec![false; rows * cols];
+
+    dist[0] = 0;
+    let mut frontier = BinaryHeap::new();
+    frontier.push(DijkstraNode(0, 0, 0));
+
+    while let Some(DijkstraNode(cost, i, j)) = frontier.pop() {
+        let here = i * cols + j;
+        if visited[here] {
+            continue;
+        }
+        visited[here] = true;
+        if cost > dist[here] {
+            continue;
+        }
+
+        if i < a.len() {
+            let next = (i + 1) * cols + j;
+            let next_cost = cost + (costs.delete)(&a[i]);
+            if next_cost < dist[next] {
+                dist[next] = next_cost;
+                prev[next] = Some(Edit::Delete);
+                frontier.push(DijkstraNode(next_cost, i + 1, j));
+            }
+        }
+
+        if j < b.len() {
+            let next = i * cols + (j + 1);
+            let next_cost = cost + (costs.insert)(&b[j]);
+            if next_cost < dist[next] {
+                dist[next] = next_cost;
+                prev[next] = Some(Edit::Insert(&b[j]));
+                frontier.push(DijkstraNode(next_cost, i, j + 1));
+            }
+        }
+
+        if i < a.len() && j < b.len() {
+            let next = (i + 1) * cols + (j + 1);
+            let edit_and_cost = if a[i] == b[j] {
+                Some((Edit::Keep, cost))
             } else {
-                current_grid_square.cost = keep_cost;
-                current_grid_square.from = Some(Rc::downgrade(keep_cell));
-                current_grid_square.edit = Edit::Keep;
+                costs.substitute.as_ref().map(|sub| (Edit::Substitute(&b[j]), cost + sub(&a[i], &b[j])))
+            };
+
+            if let Some((edit, next_cost)) = edit_and_cost {
+                if next_cost < dist[next] {
+                    dist[next] = next_cost;
+                    prev[next] = Some(edit);
+                    frontier.push(DijkstraNode(next_cost, i + 1, j + 1));
+                }
             }
         }
     }
 
-    return grid[a.len()][b.len()].borrow().path();
+    let mut edits: Vec<Edit<'a, T>> = Vec::with_capacity(rows + cols);
+    let mut i = a.len();
+    let mut j = b.len();
+
+    while i > 0 || j > 0 {
+        match prev[i * cols + j].clone().expect("no path to (i, j) found by Dijkstra") {
+            Edit::Delete => {
+                edits.push(Edit::Delete);
+                i -= 1;
+            }
+            Edit::Insert(t) => {
+                edits.push(Edit::Insert(t));
+                j -= 1;
+            }
+            edit @ (Edit::Keep | Edit::Substitute(_)) => {
+                edits.push(edit);
+                i -= 1;
+                j -= 1;
+            }
+        }
+    }
+
+    edits.reverse();
+    edits
+}
+
+/// Like `diff`, but compares elements via a derived `key` instead of `Eq` directly.
+///
+/// `key` is called exactly once per element of `a` and `b` up front, so this is a
+/// better fit than `diff` when computing the key is expensive (normalized case, a
+/// hashed blob, a trimmed token) and the DP's `O(n*m)` comparisons would otherwise
+/// recompute it over and over.
+pub fn diff_by_key<'a, T: Eq, K: Eq, F: Fn(&T) -> K>(a: &'a [T], b: &'a [T], key: F) -> Vec<Edit<'a, T>> {
+    let a_keys: Vec<K> = a.iter().map(&key).collect();
+    let b_keys: Vec<K> = b.iter().map(&key).collect();
+
+    diff_core(a, b, |i, j| a_keys[i] == b_keys[j])
 }
 
 #[cfg(test)]
@@ -168,4 +424,105 @@ mod tests {
         let c = 'c';
         assert_diff("ab", "ac", vec![Edit::Keep, Edit::Delete, Edit::Insert(&c)]);
     }
+
+    #[test]
+    fn trims_common_prefix_and_suffix() {
+        assert_diff("aaaxbbb", "aaabbb", vec![
+            Edit::Keep, Edit::Keep, Edit::Keep, Edit::Delete, Edit::Keep, Edit::Keep, Edit::Keep
+        ]);
+    }
+
+    #[test]
+    fn entirely_common_prefix_and_suffix() {
+        assert_diff("abc", "abc", vec![Edit::Keep, Edit::Keep, Edit::Keep]);
+    }
+
+    #[test]
+    fn weighted_substitutes_when_cheaper_than_delete_and_insert() {
+        let a_vec: Vec<char> = "ab".chars().collect();
+        let b_vec: Vec<char> = "ac".chars().collect();
+        let c = 'c';
+        let costs = Costs {
+            insert: 1,
+            delete: 1,
+            substitute: Some(Box::new(|_: &char, _: &char| 1)),
+        };
+        assert_eq!(diff_weighted(&a_vec, &b_vec, &costs), vec![Edit::Keep, Edit::Substitute(&c)]);
+    }
+
+    #[test]
+    fn weighted_falls_back_to_delete_and_insert_when_substitution_forbidden() {
+        let a_vec: Vec<char> = "ab".chars().collect();
+        let b_vec: Vec<char> = "ac".chars().collect();
+        let c = 'c';
+        let costs = Costs { insert: 1, delete: 1, substitute: None };
+        assert_eq!(diff_weighted(&a_vec, &b_vec, &costs), vec![Edit::Keep, Edit::Delete, Edit::Insert(&c)]);
+    }
+
+    #[test]
+    fn weighted_prefers_delete_and_insert_when_substitution_is_expensive() {
+        let a_vec: Vec<char> = "ab".chars().collect();
+        let b_vec: Vec<char> = "ac".chars().collect();
+        let c = 'c';
+        let costs = Costs {
+            insert: 1,
+            delete: 1,
+            substitute: Some(Box::new(|_: &char, _: &char| 5)),
+        };
+        assert_eq!(diff_weighted(&a_vec, &b_vec, &costs), vec![Edit::Keep, Edit::Delete, Edit::Insert(&c)]);
+    }
+
+    #[test]
+    fn dijkstra_matches_uniform_cost_diff() {
+        let a_vec: Vec<char> = "abc".chars().collect();
+        let b_vec: Vec<char> = "adc".chars().collect();
+        let c = 'd';
+        let costs = ElementCosts {
+            insert: Box::new(|_: &char| 1),
+            delete: Box::new(|_: &char| 1),
+            substitute: None,
+        };
+        assert_eq!(
+            diff_dijkstra(&a_vec, &b_vec, &costs),
+            vec![Edit::Keep, Edit::Delete, Edit::Insert(&c), Edit::Keep]
+        );
+    }
+
+    #[test]
+    fn dijkstra_prefers_cheap_insert_over_expensive_substitution() {
+        let a_vec: Vec<char> = "a".chars().collect();
+        let b_vec: Vec<char> = "ab".chars().collect();
+        let d = 'b';
+        let costs = ElementCosts {
+            insert: Box::new(|c: &char| if *c == ' ' { 1 } else { 10 }),
+            delete: Box::new(|_: &char| 10),
+            substitute: Some(Box::new(|_: &char, _: &char| 100)),
+        };
+        assert_eq!(diff_dijkstra(&a_vec, &b_vec, &costs), vec![Edit::Keep, Edit::Insert(&d)]);
+    }
+
+    #[test]
+    fn by_key_compares_on_the_derived_key() {
+        let a_vec = vec!["Hello".to_string(), "World".to_string()];
+        let b_vec = vec!["HELLO".to_string(), "there".to_string()];
+        let there = "there".to_string();
+        assert_eq!(
+            diff_by_key(&a_vec, &b_vec, |s: &String| s.to_lowercase()),
+            vec![Edit::Keep, Edit::Delete, Edit::Insert(&there)]
+        );
+    }
+
+    #[test]
+    fn by_key_calls_key_exactly_once_per_element() {
+        use std::cell::Cell;
+
+        let a_vec: Vec<char> = "ab".chars().collect();
+        let b_vec: Vec<char> = "ac".chars().collect();
+        let calls = Cell::new(0);
+        diff_by_key(&a_vec, &b_vec, |c: &char| {
+            calls.set(calls.get() + 1);
+            *c
+        });
+        assert_eq!(calls.get(), a_vec.len() + b_vec.len());
+    }
 }
\ No newline at end of file